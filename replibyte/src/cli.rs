@@ -0,0 +1,98 @@
+/// Arguments for the `dump delete` subcommand.
+#[derive(Default)]
+pub struct DumpDeleteArgs {
+    /// Delete a single dump by its directory name.
+    pub dump: Option<String>,
+    /// Delete every dump older than this duration (e.g. "7d").
+    pub older_than: Option<String>,
+    /// Keep only the N most recent dumps, deleting the rest.
+    pub keep_last: Option<usize>,
+    /// Grandfather-father-son retention: keep the newest dump in each of the
+    /// N most recent daily buckets.
+    pub keep_daily: Option<usize>,
+    /// Keep the newest dump in each of the N most recent ISO-week buckets.
+    pub keep_weekly: Option<usize>,
+    /// Keep the newest dump in each of the N most recent monthly buckets.
+    pub keep_monthly: Option<usize>,
+    /// Keep the newest dump in each of the N most recent yearly buckets.
+    pub keep_yearly: Option<usize>,
+    /// Log which dumps would be kept/pruned without deleting anything.
+    pub dry_run: bool,
+}
+
+/// Arguments for the `dump gc` subcommand.
+pub struct DumpGcArgs {
+    /// Don't remove anything younger than this duration (e.g. "1h"). Avoids
+    /// racing an in-flight `write`. Defaults to 1 hour.
+    pub grace_period: Option<String>,
+}
+
+/// A parsed `dump` subcommand, ready to run against a [`Datastore`].
+///
+/// This crate doesn't ship a `main.rs`/argv parser of its own (it's consumed
+/// as a library by the replibyte binary), so `DumpCommand` is the boundary a
+/// caller's CLI front-end builds from `std::env::args()` before handing it to
+/// [`dispatch`].
+pub enum DumpCommand {
+    Gc(DumpGcArgs),
+    Delete(DumpDeleteArgs),
+    Verify(DumpVerifyArgs),
+}
+
+/// Result of running a [`DumpCommand`].
+pub enum DumpCommandOutput {
+    Gc(crate::datastore::GarbageCollectionStatus),
+    Delete,
+    Verify(Vec<crate::datastore::BackupVerification>),
+}
+
+/// Runs a parsed [`DumpCommand`] against `datastore`, wiring each subcommand
+/// to the matching [`Datastore`] method.
+pub fn dispatch(
+    datastore: &dyn crate::datastore::Datastore,
+    command: DumpCommand,
+) -> std::io::Result<DumpCommandOutput> {
+    match command {
+        DumpCommand::Gc(args) => datastore.garbage_collect(&args).map(DumpCommandOutput::Gc),
+        DumpCommand::Delete(args) => datastore.delete(&args).map(|_| DumpCommandOutput::Delete),
+        DumpCommand::Verify(args) => datastore.verify(&args).map(DumpCommandOutput::Verify),
+    }
+}
+
+/// Arguments for the `dump verify` subcommand.
+#[derive(Default)]
+pub struct DumpVerifyArgs {
+    /// Verify a single dump by its directory name.
+    pub dump: Option<String>,
+    /// Verify every dump in the datastore.
+    pub all: bool,
+}
+
+/// Arguments for the `dump copy` subcommand.
+pub struct DumpCopyArgs {
+    /// Name of the configured datastore to copy from.
+    pub from: String,
+    /// Name of the configured datastore to copy to.
+    pub to: String,
+    /// Copy a single dump by its directory name; defaults to the latest.
+    pub dump: Option<String>,
+}
+
+/// Runs `dump copy`, copying a backup from `source` to `destination`.
+///
+/// `dump copy` needs two datastores rather than one, so unlike [`dispatch`]
+/// it isn't a [`DumpCommand`] variant. `args.from`/`args.to` name which
+/// configured datastores to use; resolving those names to a `&dyn Datastore`
+/// (e.g. looking them up in a config file) is left to the caller, same as
+/// `dispatch` expects an already-resolved datastore.
+pub fn dispatch_copy(
+    source: &dyn crate::datastore::Datastore,
+    destination: &dyn crate::datastore::Datastore,
+    args: DumpCopyArgs,
+) -> std::io::Result<()> {
+    let options = match args.dump {
+        Some(name) => crate::datastore::ReadOptions::Dump { name },
+        None => crate::datastore::ReadOptions::Latest,
+    };
+    crate::datastore::copy_backup(source, destination, &options)
+}
@@ -0,0 +1,7 @@
+use std::io::Error;
+
+/// Implemented by anything that needs to prepare its backend before use
+/// (e.g. creating an index file, opening a connection pool).
+pub trait Connector {
+    fn init(&mut self) -> Result<(), Error>;
+}
@@ -0,0 +1,49 @@
+use std::io::{Error, ErrorKind};
+
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::types;
+
+const NONCE_LEN: usize = 12;
+
+fn derive_key(key: &str) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    *Key::from_slice(&hasher.finalize())
+}
+
+/// Encrypts `data` with ChaCha20-Poly1305, prefixing the output with a random nonce.
+pub fn encrypt(data: types::Bytes, key: &str) -> Result<types::Bytes, Error> {
+    let cipher = ChaCha20Poly1305::new(&derive_key(key));
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, data.as_slice())
+        .map_err(|_| Error::new(ErrorKind::Other, "encryption failed"))?;
+
+    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Reverses [`encrypt`].
+pub fn decrypt(data: types::Bytes, key: &str) -> Result<types::Bytes, Error> {
+    if data.len() < NONCE_LEN {
+        return Err(Error::new(ErrorKind::InvalidData, "ciphertext too short"));
+    }
+
+    let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(key));
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "decryption failed"))
+}
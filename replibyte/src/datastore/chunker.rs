@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+use blake2::{Blake2s256, Digest};
+
+/// Rolling window size (in bytes) the buzhash considers when looking for a
+/// chunk boundary.
+const WINDOW_SIZE: usize = 64;
+
+/// Boundary mask tuned for a ~16 KiB average chunk size.
+const BOUNDARY_MASK: u32 = (1 << 14) - 1;
+
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A content-addressed slice produced by [`chunk_data`].
+pub struct Chunk {
+    /// Hex-encoded BLAKE2s digest of `data`, used as the chunk's storage key.
+    pub hash: String,
+    pub data: Vec<u8>,
+}
+
+/// Splits `data` into content-defined chunks using a buzhash rolling hash, so
+/// that inserting or deleting bytes only shifts the chunks around the edit
+/// instead of reshuffling every chunk after it.
+pub fn chunk_data(data: &[u8]) -> Vec<Chunk> {
+    if data.is_empty() {
+        return vec![];
+    }
+
+    let table = buzhash_table();
+    let mut chunks = Vec::new();
+    let mut window: VecDeque<u8> = VecDeque::with_capacity(WINDOW_SIZE);
+    let mut hash: u32 = 0;
+    let mut start = 0usize;
+
+    for (i, &byte) in data.iter().enumerate() {
+        let outgoing = if window.len() == WINDOW_SIZE {
+            window.pop_front()
+        } else {
+            None
+        };
+        hash = hash.rotate_left(1) ^ table[byte as usize];
+        if let Some(outgoing) = outgoing {
+            // The outgoing byte's contribution was folded into `hash` via the
+            // rotate above `WINDOW_SIZE` steps ago, so it must be cancelled
+            // *after* that rotation, not before, or its rotation amount ends
+            // up off by one and the hash never forgets bytes outside the
+            // window.
+            hash ^= table[outgoing as usize].rotate_left(WINDOW_SIZE as u32);
+        }
+        window.push_back(byte);
+
+        let chunk_len = i - start + 1;
+        let at_boundary = chunk_len >= MIN_CHUNK_SIZE
+            && (hash & BOUNDARY_MASK == 0 || chunk_len >= MAX_CHUNK_SIZE);
+
+        if at_boundary {
+            chunks.push(make_chunk(&data[start..=i]));
+            start = i + 1;
+            hash = 0;
+            window.clear();
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(make_chunk(&data[start..]));
+    }
+
+    chunks
+}
+
+fn make_chunk(bytes: &[u8]) -> Chunk {
+    Chunk {
+        hash: digest_hex(bytes),
+        data: bytes.to_vec(),
+    }
+}
+
+/// Hex-encoded BLAKE2s digest, shared with the chunk store so lookups and
+/// verification use the same hash.
+pub fn digest_hex(bytes: &[u8]) -> String {
+    let mut hasher = Blake2s256::new();
+    hasher.update(bytes);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chunk_data;
+
+    // chunk hashes that lie entirely after an edit point should be unaffected
+    // by it, once the edit is far enough behind them to have left the
+    // rolling window (otherwise the hash is carrying unbounded history
+    // instead of depending only on the trailing WINDOW_SIZE bytes).
+    #[test]
+    fn chunks_after_an_edit_are_unaffected_by_it() {
+        // xorshift64 PRNG so the data isn't so periodic that unrelated
+        // windows accidentally hash the same way
+        let mut original = vec![0u8; 200 * 1024];
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        for byte in original.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *byte = (state & 0xFF) as u8;
+        }
+
+        let mut edited = original.clone();
+        edited.insert(100_000, 0xAB);
+
+        let original_chunks = chunk_data(&original);
+        let edited_chunks = chunk_data(&edited);
+
+        let original_hashes: std::collections::HashSet<_> =
+            original_chunks.iter().map(|c| c.hash.clone()).collect();
+        let edited_hashes: std::collections::HashSet<_> =
+            edited_chunks.iter().map(|c| c.hash.clone()).collect();
+
+        // chunks fully before the insertion point match exactly, and at
+        // least one chunk after it should re-sync and match too
+        let matching = original_hashes.intersection(&edited_hashes).count();
+        assert!(
+            matching >= original_chunks.len() - 1,
+            "expected nearly all chunks to survive the edit, only {} of {} matched",
+            matching,
+            original_chunks.len()
+        );
+    }
+}
+
+fn buzhash_table() -> &'static [u32; 256] {
+    // Deterministic pseudo-random table (splitmix64-derived) so the same
+    // bytes always hash the same way across processes and versions.
+    static TABLE: once_cell::sync::Lazy<[u32; 256]> = once_cell::sync::Lazy::new(|| {
+        let mut table = [0u32; 256];
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        for slot in table.iter_mut() {
+            seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^= z >> 31;
+            *slot = (z & 0xFFFFFFFF) as u32;
+        }
+        table
+    });
+    &TABLE
+}
@@ -1,16 +1,32 @@
-use std::fs::{read, read_dir, remove_dir_all, write, DirBuilder, OpenOptions};
-use std::io::{BufReader, Error, Read, Write};
+use std::collections::HashSet;
+use std::fs::{read, read_dir, remove_file, write, DirBuilder, OpenOptions};
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
 
+use chrono::Duration;
 use log::{debug, error, info};
 
+use crate::cli::{DumpGcArgs, DumpVerifyArgs};
 use crate::connector::Connector;
 use crate::types;
 use crate::utils::epoch_millis;
 
+use super::chunker::{self, chunk_data};
 use super::{
-    compress, decompress, decrypt, encrypt, Backup, Datastore, IndexFile, INDEX_FILE_NAME,
+    compress, decompress, decrypt, encrypt, parse_duration, Backup, BackupVerification,
+    Datastore, GarbageCollectionStatus, IndexFile, PartManifest, PartVerification,
+    PartVerificationStatus, INDEX_FILE_NAME,
 };
 
+/// Name of the file the index is stored under once it's encrypted, distinct
+/// from [`INDEX_FILE_NAME`] so a reader can't mistake one for the other.
+const METADATA_FILE_NAME: &str = "metadata.enc";
+
+/// Leading cleartext byte of [`METADATA_FILE_NAME`] marking the rest of the
+/// file as an encrypted index.
+const METADATA_ENCRYPTED_HEADER: u8 = 0x01;
+
 pub struct LocalDisk {
     dir: String,
     dump_name: String,
@@ -38,6 +54,125 @@ impl LocalDisk {
             }
         }
     }
+
+    /// Re-reads `part`'s chunks off disk and compares them against the size
+    /// and digest recorded at write time.
+    fn verify_part(&self, part: &PartManifest) -> Result<PartVerificationStatus, Error> {
+        let mut data = Vec::with_capacity(part.size);
+
+        for chunk_hash in &part.chunks {
+            let chunk_file_path = format!("{}/chunks/{}/{}", self.dir, &chunk_hash[..2], chunk_hash);
+
+            match read(chunk_file_path) {
+                Ok(bytes) => data.extend(bytes),
+                Err(_) => return Ok(PartVerificationStatus::Missing),
+            }
+        }
+
+        if data.len() != part.size || chunker::digest_hex(&data) != part.digest {
+            return Ok(PartVerificationStatus::Corrupt);
+        }
+
+        Ok(PartVerificationStatus::Ok)
+    }
+
+    /// Chunks `data` (already compressed/encrypted, or raw bytes copied
+    /// verbatim from another datastore) and records it as `file_part` of the
+    /// backup named `directory_name`, creating the backup in the index if
+    /// this is its first part.
+    fn store_part(
+        &self,
+        directory_name: &str,
+        file_part: u16,
+        data: types::Bytes,
+        compressed: bool,
+        encrypted: bool,
+    ) -> Result<(), Error> {
+        // digest taken over the final on-disk bytes so `verify` can catch
+        // bit-rot without needing the encryption key
+        let part_size = data.len();
+        let part_digest = chunker::digest_hex(&data);
+
+        // split into content-defined chunks so that a part sharing most of
+        // its bytes with one already stored only persists the new pieces
+        let chunks_dir_path = format!("{}/chunks", self.dir);
+        let mut chunk_hashes = Vec::new();
+        let mut new_bytes_written = 0usize;
+
+        for chunk in chunk_data(&data) {
+            chunk_hashes.push(chunk.hash.clone());
+
+            let chunk_dir_path = format!("{}/{}", chunks_dir_path, &chunk.hash[..2]);
+            let chunk_file_path = format!("{}/{}", chunk_dir_path, chunk.hash);
+
+            if Path::new(&chunk_file_path).exists() {
+                continue;
+            }
+
+            DirBuilder::new()
+                .recursive(true)
+                .create(&chunk_dir_path)
+                .map_err(|err| {
+                    error!("error while creating the chunk directory: {}", chunk_dir_path);
+                    err
+                })?;
+
+            debug!("writing chunk at: {}", chunk_file_path);
+            write(&chunk_file_path, &chunk.data).map_err(|err| {
+                error!("error while writing chunk at: {}", chunk_file_path);
+                err
+            })?;
+
+            new_bytes_written += chunk.data.len();
+        }
+
+        debug!(
+            "part {} of {} wrote {} new bytes to disk ({} bytes deduplicated away)",
+            file_part,
+            directory_name,
+            new_bytes_written,
+            part_size.saturating_sub(new_bytes_written)
+        );
+
+        // update index file
+        let mut index_file = self.index_file()?;
+
+        let part = PartManifest {
+            file_part,
+            chunks: chunk_hashes,
+            size: part_size,
+            digest: part_digest,
+        };
+
+        match index_file
+            .backups
+            .iter_mut()
+            .find(|b| b.directory_name.as_str() == directory_name)
+        {
+            Some(backup) => {
+                match backup.parts.iter_mut().find(|p| p.file_part == file_part) {
+                    Some(existing) => *existing = part,
+                    None => backup.parts.push(part),
+                }
+                // `size` is the backup's true logical size (sum of every
+                // part's on-disk bytes), not the bytes newly written to disk
+                // by this call — those two diverge as soon as a part's
+                // chunks are deduplicated against another backup.
+                backup.size = backup.parts.iter().map(|p| p.size).sum();
+            }
+            None => index_file.backups.push(Backup {
+                directory_name: directory_name.to_string(),
+                size: part_size,
+                created_at: epoch_millis(),
+                compressed,
+                encrypted,
+                parts: vec![part],
+            }),
+        }
+
+        // save index file
+        self.write_index_file(&index_file)
+    }
 }
 
 impl Connector for LocalDisk {
@@ -54,6 +189,30 @@ impl Datastore for LocalDisk {
             &self.dir
         );
 
+        let metadata_file_path = format!("{}/{}", self.dir, METADATA_FILE_NAME);
+
+        if Path::new(&metadata_file_path).exists() {
+            let mut bytes = read(&metadata_file_path)?;
+            if bytes.is_empty() || bytes.remove(0) != METADATA_ENCRYPTED_HEADER {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "unrecognized metadata file header",
+                ));
+            }
+
+            let encryption_key = self.encryption_key.as_ref().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Other,
+                    "datastore has no encryption_key set to read its encrypted index file",
+                )
+            })?;
+
+            let json = decrypt(bytes, encryption_key.as_str())?;
+            return serde_json::from_slice(&json).map_err(|err| Error::from(err));
+        }
+
+        // no encrypted index: read the plaintext index file, written when no
+        // encryption_key is set (or before encryption was supported)
         let file = OpenOptions::new()
             .read(true)
             .open(format!("{}/{}", self.dir, INDEX_FILE_NAME))?;
@@ -68,17 +227,49 @@ impl Datastore for LocalDisk {
 
     fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error> {
         info!("writing index_file to local_disk datastore");
-        let index_file_path = format!("{}/{}", self.dir, INDEX_FILE_NAME);
-
-        debug!("opening index_file at {}", index_file_path);
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&index_file_path)?;
 
-        debug!("writing index_file at {}", index_file_path.as_str());
-        serde_json::to_writer(file, index_file).map_err(|err| Error::from(err))
+        match self.encryption_key() {
+            Some(key) => {
+                let json = serde_json::to_vec(index_file).map_err(|err| Error::from(err))?;
+                let encrypted = encrypt(json, key.as_str())?;
+
+                let metadata_file_path = format!("{}/{}", self.dir, METADATA_FILE_NAME);
+                debug!("writing encrypted index_file at {}", metadata_file_path);
+
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(&metadata_file_path)?;
+
+                file.write_all(&[METADATA_ENCRYPTED_HEADER])?;
+                file.write_all(&encrypted)?;
+
+                // remove any stale plaintext index left over from before
+                // encryption was enabled on this datastore, so the old
+                // backup names/sizes/timestamps don't linger on disk in the
+                // clear next to the now-authoritative encrypted copy
+                let index_file_path = format!("{}/{}", self.dir, INDEX_FILE_NAME);
+                if Path::new(&index_file_path).exists() {
+                    remove_file(&index_file_path)?;
+                }
+
+                Ok(())
+            }
+            None => {
+                let index_file_path = format!("{}/{}", self.dir, INDEX_FILE_NAME);
+
+                debug!("opening index_file at {}", index_file_path);
+                let file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(&index_file_path)?;
+
+                debug!("writing index_file at {}", index_file_path.as_str());
+                serde_json::to_writer(file, index_file).map_err(|err| Error::from(err))
+            }
+        }
     }
 
     fn write(&self, file_part: u16, data: types::Bytes) -> Result<(), Error> {
@@ -95,55 +286,13 @@ impl Datastore for LocalDisk {
             None => data,
         };
 
-        let data_size = data.len();
-        let dump_dir_path = format!("{}/{}", self.dir, self.dump_name);
-        let dump_file_path = format!("{}/{}.dump", dump_dir_path, file_part);
-
-        // create the dump directory if needed
-        DirBuilder::new()
-            .recursive(true)
-            .create(&dump_dir_path)
-            .map_err(|err| {
-                error!("error while creating the dump directory: {}", dump_dir_path);
-                err
-            })?;
-
-        debug!("writing dump at: {}", dump_file_path);
-        let _ = write(&dump_file_path, data).map_err(|err| {
-            error!("error while writing dumpt at: {}", dump_file_path);
-            err
-        })?;
-
-        // update index file
-        let mut index_file = self.index_file()?;
-
-        let mut new_backup = Backup {
-            directory_name: self.dump_name.to_string(),
-            size: 0,
-            created_at: epoch_millis(),
-            compressed: self.compression_enabled(),
-            encrypted: self.encryption_key().is_some(),
-        };
-
-        // find or create Backup
-        let mut backup = index_file
-            .backups
-            .iter_mut()
-            .find(|b| b.directory_name.as_str() == self.dump_name)
-            .unwrap_or(&mut new_backup);
-
-        if backup.size == 0 {
-            // it means it's a new backup.
-            // We need to add it into the index_file.backups
-            new_backup.size = data_size;
-            index_file.backups.push(new_backup);
-        } else {
-            // update total backup size
-            backup.size = backup.size + data_size;
-        }
-
-        // save index file
-        self.write_index_file(&index_file)
+        self.store_part(
+            &self.dump_name.clone(),
+            file_part,
+            data,
+            self.compression_enabled(),
+            self.encryption_key().is_some(),
+        )
     }
 
     fn read(
@@ -151,13 +300,14 @@ impl Datastore for LocalDisk {
         options: &super::ReadOptions,
         data_callback: &mut dyn FnMut(types::Bytes),
     ) -> Result<(), Error> {
-        let mut index_file = self.index_file()?;
+        let index_file = self.index_file()?;
         let backup = index_file.find_backup(options)?;
-        let entries = read_dir(format!("{}/{}", self.dir, backup.directory_name))?;
 
-        for entry in entries {
-            let entry = entry?;
-            let data = read(entry.path())?;
+        let mut parts = backup.parts.clone();
+        parts.sort_by_key(|p| p.file_part);
+
+        for part in parts {
+            let data = self.read_part_raw(&backup.directory_name, part.file_part)?;
 
             // decrypt data?
             let data = if backup.encrypted {
@@ -182,6 +332,48 @@ impl Datastore for LocalDisk {
         Ok(())
     }
 
+    fn read_part_raw(&self, directory_name: &str, file_part: u16) -> Result<types::Bytes, Error> {
+        let index_file = self.index_file()?;
+
+        let backup = index_file
+            .backups
+            .iter()
+            .find(|b| b.directory_name == directory_name)
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("no backup named {}", directory_name))
+            })?;
+
+        let part = backup
+            .parts
+            .iter()
+            .find(|p| p.file_part == file_part)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("no part {} in backup {}", file_part, directory_name),
+                )
+            })?;
+
+        let mut data = Vec::with_capacity(part.size);
+        for chunk_hash in &part.chunks {
+            let chunk_file_path = format!("{}/chunks/{}/{}", self.dir, &chunk_hash[..2], chunk_hash);
+            data.extend(read(chunk_file_path)?);
+        }
+
+        Ok(data)
+    }
+
+    fn write_part_raw(
+        &self,
+        directory_name: &str,
+        file_part: u16,
+        data: types::Bytes,
+        compressed: bool,
+        encrypted: bool,
+    ) -> Result<(), Error> {
+        self.store_part(directory_name, file_part, data, compressed, encrypted)
+    }
+
     fn compression_enabled(&self) -> bool {
         self.enable_compression
     }
@@ -208,18 +400,125 @@ impl Datastore for LocalDisk {
     }
 
     fn delete_by_name(&self, name: String) -> Result<(), Error> {
+        // Chunks may be shared with other backups, so we can't simply
+        // `remove_dir_all` a per-backup directory anymore: dropping a backup
+        // from the index just un-references its chunks. Reclaiming the
+        // chunks that become orphaned this way is `garbage_collect`'s job.
         let mut index_file = self.index_file()?;
+        index_file.backups.retain(|b| b.directory_name != name);
+        self.write_index_file(&index_file)
+    }
 
-        let dump_dir_path = format!("{}/{}", self.dir, name);
-        remove_dir_all(&dump_dir_path).map_err(|err| {
-            error!("error while removing the dump directory: {}", dump_dir_path);
-            err
-        })?;
+    fn garbage_collect(&self, args: &DumpGcArgs) -> Result<GarbageCollectionStatus, Error> {
+        let grace_period = match &args.grace_period {
+            Some(value) => parse_duration(value)?,
+            None => Duration::hours(1),
+        }
+        .to_std()
+        .map_err(|err| Error::new(std::io::ErrorKind::InvalidInput, err))?;
 
-        // update the index_file.
-        index_file.backups.retain(|b| b.directory_name != name);
+        let index_file = self.index_file()?;
+        let referenced_chunks: HashSet<&str> = index_file
+            .backups
+            .iter()
+            .flat_map(|backup| backup.parts.iter())
+            .flat_map(|part| part.chunks.iter())
+            .map(|hash| hash.as_str())
+            .collect();
+
+        let mut status = GarbageCollectionStatus::default();
+        let chunks_dir_path = format!("{}/chunks", self.dir);
+
+        let prefix_dirs = match read_dir(&chunks_dir_path) {
+            Ok(entries) => entries,
+            // nothing has been written yet, there is nothing to collect
+            Err(_) => return Ok(status),
+        };
 
-        self.write_index_file(&index_file)
+        for prefix_dir in prefix_dirs {
+            let prefix_dir = prefix_dir?;
+            if !prefix_dir.file_type()?.is_dir() {
+                continue;
+            }
+
+            for entry in read_dir(prefix_dir.path())? {
+                let entry = entry?;
+                let hash = entry.file_name().to_string_lossy().to_string();
+
+                if referenced_chunks.contains(hash.as_str()) {
+                    continue;
+                }
+
+                let metadata = entry.metadata()?;
+                let age = SystemTime::now()
+                    .duration_since(metadata.modified()?)
+                    .unwrap_or_default();
+
+                // a chunk that was just written might not be referenced by
+                // the index file yet, give it time to land before sweeping it
+                if age < grace_period {
+                    continue;
+                }
+
+                debug!("removing orphaned chunk at: {:?}", entry.path());
+                remove_file(entry.path()).map_err(|err| {
+                    error!("error while removing orphaned chunk at: {:?}", entry.path());
+                    err
+                })?;
+
+                status.bytes_freed += metadata.len();
+                status.objects_removed += 1;
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn verify(&self, args: &DumpVerifyArgs) -> Result<Vec<BackupVerification>, Error> {
+        let index_file = self.index_file()?;
+
+        let backups: Vec<&Backup> = match (&args.dump, args.all) {
+            (Some(_), true) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "--dump and --all are mutually exclusive",
+                ))
+            }
+            (Some(name), false) => vec![index_file
+                .backups
+                .iter()
+                .find(|b| b.directory_name.as_str() == name.as_str())
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::NotFound, format!("no backup named {}", name))
+                })?],
+            (None, true) => index_file.backups.iter().collect(),
+            (None, false) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "verify requires either --dump <name> or --all",
+                ))
+            }
+        };
+
+        let mut report = Vec::with_capacity(backups.len());
+
+        for backup in backups {
+            let mut parts = Vec::with_capacity(backup.parts.len());
+
+            for part in &backup.parts {
+                parts.push(PartVerification {
+                    file_part: part.file_part,
+                    status: self.verify_part(part)?,
+                });
+            }
+
+            report.push(BackupVerification {
+                directory_name: backup.directory_name.clone(),
+                parts,
+            });
+        }
+
+        Ok(report)
     }
 }
 
@@ -231,14 +530,24 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
-        cli::DumpDeleteArgs,
+        cli::{DumpDeleteArgs, DumpGcArgs, DumpVerifyArgs},
         connector::Connector,
-        datastore::{Backup, Datastore, ReadOptions},
+        datastore::{self, Backup, Datastore, PartVerificationStatus, ReadOptions},
         utils::epoch_millis,
     };
 
     use super::LocalDisk;
 
+    // backup_exists checks whether a backup is still referenced by the index file.
+    fn backup_exists(local_disk: &LocalDisk, dump_name: &str) -> bool {
+        local_disk
+            .index_file()
+            .unwrap()
+            .backups
+            .iter()
+            .any(|b| b.directory_name == dump_name)
+    }
+
     // update_backup_date is a helper function that updates the date of a dump inside the index file.
     fn update_backup_date(local_disk: &LocalDisk, dump_name: String, days_before_now: i64) {
         let mut index_file = local_disk.index_file().unwrap();
@@ -280,13 +589,18 @@ mod tests {
 
         let dump = index_file.find_backup(&ReadOptions::Latest).unwrap();
 
-        // part 1 of dump should exists
-        assert!(Path::new(&format!(
-            "{}/{}/1.dump",
-            dir.path().to_str().unwrap(),
-            dump.directory_name
-        ))
-        .exists());
+        // every chunk making up part 1 of the dump should exist on disk
+        assert_eq!(dump.parts.len(), 1);
+        let part = &dump.parts[0];
+        for chunk_hash in &part.chunks {
+            assert!(Path::new(&format!(
+                "{}/chunks/{}/{}",
+                dir.path().to_str().unwrap(),
+                &chunk_hash[..2],
+                chunk_hash
+            ))
+            .exists());
+        }
 
         let mut dump_content: Vec<u8> = vec![];
         assert!(local_disk
@@ -298,6 +612,58 @@ mod tests {
         assert_eq!(dump_content, b"hello world".to_vec())
     }
 
+    #[test]
+    fn test_rewriting_a_part_replaces_it_instead_of_duplicating() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        assert!(local_disk.write(0, b"hello world".to_vec()).is_ok());
+        // writing the same file_part again (e.g. a retried write) should
+        // replace it, not append a second copy
+        assert!(local_disk.write(0, b"hello world".to_vec()).is_ok());
+
+        let index_file = local_disk.index_file().unwrap();
+        let dump = index_file.find_backup(&ReadOptions::Latest).unwrap();
+        assert_eq!(dump.parts.len(), 1);
+
+        let mut dump_content: Vec<u8> = vec![];
+        assert!(local_disk
+            .read(&ReadOptions::Latest, &mut |bytes| {
+                let mut b = bytes;
+                dump_content.append(&mut b);
+            })
+            .is_ok());
+        assert_eq!(dump_content, b"hello world".to_vec())
+    }
+
+    #[test]
+    fn test_backup_size_reflects_logical_size_even_when_fully_deduplicated() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let payload: Vec<u8> = (0..20 * 1024).map(|i| (i % 7) as u8).collect();
+
+        local_disk.set_dump_name("dump-1".to_string());
+        assert!(local_disk.write(0, payload.clone()).is_ok());
+
+        // dump-2 shares every chunk with dump-1, so nothing new is written
+        // to disk for it, but its backup `size` must still report the
+        // backup's real, readable content size rather than ~0
+        local_disk.set_dump_name("dump-2".to_string());
+        assert!(local_disk.write(0, payload).is_ok());
+
+        let index_file = local_disk.index_file().unwrap();
+        let dump_2 = index_file
+            .find_backup(&ReadOptions::Dump {
+                name: "dump-2".to_string(),
+            })
+            .unwrap();
+
+        assert!(dump_2.size > 0);
+    }
+
     #[test]
     fn test_index_file() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -316,6 +682,7 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            parts: vec![],
         });
 
         assert!(local_disk.write_index_file(&index_file).is_ok());
@@ -323,6 +690,26 @@ mod tests {
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
     }
 
+    #[test]
+    fn test_enabling_encryption_removes_stale_plaintext_index() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let index_file_path = dir.path().join(super::INDEX_FILE_NAME);
+        assert!(index_file_path.exists());
+
+        // enabling encryption on a datastore that already has a plaintext
+        // index file should leave no plaintext copy of the metadata behind
+        local_disk.set_encryption_key("super-secret-key".to_string());
+        assert!(local_disk
+            .write_index_file(&local_disk.index_file().unwrap())
+            .is_ok());
+
+        assert!(!index_file_path.exists());
+        assert!(local_disk.index_file().is_ok());
+    }
+
     #[test]
     fn test_backup_name() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -349,36 +736,58 @@ mod tests {
         let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
-        assert!(Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-1"));
 
         // create dump 2
         local_disk.set_dump_name("dump-2".to_string());
         let bytes: Vec<u8> = b"hello world from dump-2".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 2);
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-2"));
 
         // remove dump 1
         assert!(local_disk
             .delete(&DumpDeleteArgs {
                 dump: Some("dump-1".to_string()),
                 older_than: None,
-                keep_last: None
+                keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
-        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-1"));
 
         // remove dump 2
         assert!(local_disk
             .delete(&DumpDeleteArgs {
                 dump: Some("dump-2".to_string()),
                 older_than: None,
-                keep_last: None
+                keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 0);
-        assert!(!Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-2"));
+    }
+
+    #[test]
+    fn test_delete_by_name_dry_run_does_not_delete() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+
+        let _ = local_disk.init().expect("local_disk init failed");
+        local_disk.set_dump_name("dump-1".to_string());
+        assert!(local_disk.write(1, b"hello world from dump-1".to_vec()).is_ok());
+        assert!(backup_exists(&local_disk, "dump-1"));
+
+        assert!(local_disk
+            .delete(&DumpDeleteArgs {
+                dump: Some("dump-1".to_string()),
+                dry_run: true,
+                ..Default::default()
+            })
+            .is_ok());
+        assert!(backup_exists(&local_disk, "dump-1"));
     }
 
     #[test]
@@ -397,47 +806,49 @@ mod tests {
         let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
-        assert!(Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-1"));
 
         // create dump 2
         local_disk.set_dump_name("dump-2".to_string());
         let bytes: Vec<u8> = b"hello world from dump-2".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 2);
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-2"));
 
         // create dump 3
         local_disk.set_dump_name("dump-3".to_string());
         let bytes: Vec<u8> = b"hello world from dump-3".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 3);
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         assert!(local_disk
             .delete(&DumpDeleteArgs {
                 dump: None,
                 older_than: None,
                 keep_last: Some(2),
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 2);
         // only dump-1 must be deleted
-        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-1"));
+        assert!(backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         assert!(local_disk
             .delete(&DumpDeleteArgs {
                 dump: None,
                 older_than: None,
                 keep_last: Some(1),
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
         // only dump-3 must exists
-        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
-        assert!(!Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-1"));
+        assert!(!backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
     }
 
     #[test]
@@ -456,7 +867,7 @@ mod tests {
         let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
-        assert!(Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-1"));
         update_backup_date(&local_disk, "dump-1".to_string(), 5);
 
         // create dump 2
@@ -464,7 +875,7 @@ mod tests {
         let bytes: Vec<u8> = b"hello world from dump-2".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 2);
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-2"));
         update_backup_date(&local_disk, "dump-2".to_string(), 3);
 
         // create dump 3
@@ -472,7 +883,7 @@ mod tests {
         let bytes: Vec<u8> = b"hello world from dump-3".to_vec();
         assert!(local_disk.write(1, bytes).is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 3);
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         // delete dump older than 6 days doesn't remove any dump
         assert!(local_disk
@@ -480,12 +891,13 @@ mod tests {
                 dump: None,
                 older_than: Some("6d".to_string()),
                 keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 3);
-        assert!(Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(backup_exists(&local_disk, "dump-1"));
+        assert!(backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         // delete dump older than 4 days must remove dump-1
         assert!(local_disk
@@ -493,12 +905,13 @@ mod tests {
                 dump: None,
                 older_than: Some("4d".to_string()),
                 keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 2);
-        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-1"));
+        assert!(backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         // delete dump older than 1 day must remove dump-2
         assert!(local_disk
@@ -506,12 +919,13 @@ mod tests {
                 dump: None,
                 older_than: Some("1d".to_string()),
                 keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
-        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
-        assert!(!Path::new(&format!("{}/dump-2", dir.path().to_str().unwrap())).exists());
-        assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-1"));
+        assert!(!backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
 
         // delete dump older than 0 day must remove dump-3
         assert!(local_disk
@@ -519,9 +933,239 @@ mod tests {
                 dump: None,
                 older_than: Some("0d".to_string()),
                 keep_last: None,
+                ..Default::default()
             })
             .is_ok());
         assert_eq!(local_disk.index_file().unwrap().backups.len(), 0);
-        assert!(!Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
+        assert!(!backup_exists(&local_disk, "dump-3"));
+    }
+
+    #[test]
+    fn test_garbage_collect() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        local_disk.set_dump_name("dump-1".to_string());
+        let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
+        assert!(local_disk.write(1, bytes).is_ok());
+
+        let chunk_hash = local_disk
+            .index_file()
+            .unwrap()
+            .find_backup(&ReadOptions::Latest)
+            .unwrap()
+            .parts[0]
+            .chunks[0]
+            .clone();
+        let chunk_path = format!(
+            "{}/chunks/{}/{}",
+            dir.path().to_str().unwrap(),
+            &chunk_hash[..2],
+            chunk_hash
+        );
+        assert!(Path::new(&chunk_path).exists());
+
+        // deleting the backup un-references its chunk but doesn't remove it from disk
+        assert!(local_disk
+            .delete(&DumpDeleteArgs {
+                dump: Some("dump-1".to_string()),
+                older_than: None,
+                keep_last: None,
+                ..Default::default()
+            })
+            .is_ok());
+        assert!(Path::new(&chunk_path).exists());
+
+        let status = local_disk
+            .garbage_collect(&DumpGcArgs {
+                grace_period: Some("0h".to_string()),
+            })
+            .unwrap();
+
+        assert_eq!(status.objects_removed, 1);
+        assert!(!Path::new(&chunk_path).exists());
+    }
+
+    #[test]
+    fn test_encrypted_index_file() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.set_encryption_key("super-secret-key".to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        // the index must be stored encrypted, not as plaintext JSON
+        assert!(!Path::new(&format!("{}/index.json", dir.path().to_str().unwrap())).exists());
+        assert!(Path::new(&format!("{}/metadata.enc", dir.path().to_str().unwrap())).exists());
+
+        local_disk.set_dump_name("dump-1".to_string());
+        let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
+        assert!(local_disk.write(1, bytes).is_ok());
+
+        assert_eq!(local_disk.index_file().unwrap().backups.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_keep_daily() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        for (name, days_ago) in [("dump-1", 3), ("dump-2", 2), ("dump-3", 1), ("dump-4", 0)] {
+            local_disk.set_dump_name(name.to_string());
+            let bytes: Vec<u8> = format!("hello world from {}", name).into_bytes();
+            assert!(local_disk.write(1, bytes).is_ok());
+            update_backup_date(&local_disk, name.to_string(), days_ago);
+        }
+
+        assert!(local_disk
+            .delete(&DumpDeleteArgs {
+                keep_daily: Some(2),
+                ..Default::default()
+            })
+            .is_ok());
+
+        // only the 2 most recent daily buckets survive
+        assert!(!backup_exists(&local_disk, "dump-1"));
+        assert!(!backup_exists(&local_disk, "dump-2"));
+        assert!(backup_exists(&local_disk, "dump-3"));
+        assert!(backup_exists(&local_disk, "dump-4"));
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        local_disk.set_dump_name("dump-1".to_string());
+        let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
+        assert!(local_disk.write(1, bytes).is_ok());
+
+        // a freshly written dump verifies clean
+        let report = local_disk
+            .verify(&DumpVerifyArgs {
+                all: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].is_healthy());
+
+        // corrupt the chunk backing the only part
+        let chunk_hash = local_disk
+            .index_file()
+            .unwrap()
+            .find_backup(&ReadOptions::Latest)
+            .unwrap()
+            .parts[0]
+            .chunks[0]
+            .clone();
+        let chunk_path = format!(
+            "{}/chunks/{}/{}",
+            dir.path().to_str().unwrap(),
+            &chunk_hash[..2],
+            chunk_hash
+        );
+        std::fs::write(&chunk_path, b"corrupted").unwrap();
+
+        let report = local_disk
+            .verify(&DumpVerifyArgs {
+                dump: Some("dump-1".to_string()),
+                all: false,
+            })
+            .unwrap();
+        assert!(!report[0].is_healthy());
+        assert_eq!(report[0].parts[0].status, PartVerificationStatus::Corrupt);
+    }
+
+    #[test]
+    fn test_verify_requires_exactly_one_of_dump_or_all() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        // neither flag set
+        assert!(local_disk.verify(&DumpVerifyArgs::default()).is_err());
+
+        // both flags set
+        assert!(local_disk
+            .verify(&DumpVerifyArgs {
+                dump: Some("dump-1".to_string()),
+                all: true,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_copy_backup() {
+        let source_dir = tempdir().expect("cannot create tempdir");
+        let mut source = LocalDisk::new(source_dir.path().to_str().unwrap().to_string());
+        let _ = source.init().expect("local_disk init failed");
+        source.set_encryption_key("super-secret-key".to_string());
+
+        source.set_dump_name("dump-1".to_string());
+        let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
+        assert!(source.write(1, bytes).is_ok());
+
+        let destination_dir = tempdir().expect("cannot create tempdir");
+        let mut destination =
+            LocalDisk::new(destination_dir.path().to_str().unwrap().to_string());
+        let _ = destination.init().expect("local_disk init failed");
+        destination.set_encryption_key("super-secret-key".to_string());
+
+        assert!(datastore::copy_backup(&source, &destination, &ReadOptions::Latest).is_ok());
+
+        let destination_backup = destination
+            .index_file()
+            .unwrap()
+            .find_backup(&ReadOptions::Latest)
+            .unwrap()
+            .clone();
+        let source_backup = source
+            .index_file()
+            .unwrap()
+            .find_backup(&ReadOptions::Latest)
+            .unwrap()
+            .clone();
+
+        assert_eq!(destination_backup.directory_name, source_backup.directory_name);
+        assert_eq!(destination_backup.compressed, source_backup.compressed);
+        assert_eq!(destination_backup.encrypted, source_backup.encrypted);
+        assert_eq!(destination_backup.created_at, source_backup.created_at);
+
+        // the copied backup must read back identically on the destination
+        let mut dump_content: Vec<u8> = vec![];
+        assert!(destination
+            .read(&ReadOptions::Latest, &mut |bytes| {
+                let mut b = bytes;
+                dump_content.append(&mut b);
+            })
+            .is_ok());
+        assert_eq!(dump_content, b"hello world from dump-1".to_vec());
+    }
+
+    #[test]
+    fn test_copy_backup_rejects_mismatched_encryption_keys() {
+        let source_dir = tempdir().expect("cannot create tempdir");
+        let mut source = LocalDisk::new(source_dir.path().to_str().unwrap().to_string());
+        let _ = source.init().expect("local_disk init failed");
+        source.set_encryption_key("source-key".to_string());
+
+        source.set_dump_name("dump-1".to_string());
+        let bytes: Vec<u8> = b"hello world from dump-1".to_vec();
+        assert!(source.write(1, bytes).is_ok());
+
+        let destination_dir = tempdir().expect("cannot create tempdir");
+        let mut destination =
+            LocalDisk::new(destination_dir.path().to_str().unwrap().to_string());
+        let _ = destination.init().expect("local_disk init failed");
+        destination.set_encryption_key("destination-key".to_string());
+
+        // copying an encrypted backup across mismatched keys would leave the
+        // destination with ciphertext it can never decrypt, so the copy must
+        // be rejected up front instead of silently losing the backup
+        assert!(datastore::copy_backup(&source, &destination, &ReadOptions::Latest).is_err());
+        assert!(destination.index_file().unwrap().backups.is_empty());
     }
 }
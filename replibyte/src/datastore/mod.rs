@@ -0,0 +1,364 @@
+use std::collections::HashSet;
+use std::io::{Cursor, Error, ErrorKind, Read, Write};
+
+use chrono::{Datelike, Duration, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{DumpDeleteArgs, DumpGcArgs, DumpVerifyArgs};
+use crate::types;
+
+pub mod chunker;
+pub mod local_disk;
+
+/// Name of the file (at the datastore root) that tracks every backup.
+pub const INDEX_FILE_NAME: &str = "index.json";
+
+/// Ordered list of chunk hashes making up one `file_part` of a [`Backup`],
+/// stored in place of the old single `{part}.dump` blob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartManifest {
+    pub file_part: u16,
+    pub chunks: Vec<String>,
+    /// Byte length of the part's final on-disk (compressed/encrypted) bytes.
+    pub size: usize,
+    /// BLAKE2s digest of the part's final on-disk bytes, checked by `verify`.
+    pub digest: String,
+}
+
+/// A single backup stored in a datastore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub directory_name: String,
+    pub size: usize,
+    pub created_at: u128,
+    pub compressed: bool,
+    pub encrypted: bool,
+    pub parts: Vec<PartManifest>,
+}
+
+/// The set of backups known to a datastore, persisted as [`INDEX_FILE_NAME`]
+/// (or, when the datastore has an encryption key, in encrypted form).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexFile {
+    pub backups: Vec<Backup>,
+}
+
+impl IndexFile {
+    /// Resolves a [`ReadOptions`] to the matching [`Backup`].
+    pub fn find_backup(&self, options: &ReadOptions) -> Result<&Backup, Error> {
+        match options {
+            ReadOptions::Latest => self
+                .backups
+                .iter()
+                .max_by_key(|b| b.created_at)
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "no backup found")),
+            ReadOptions::Dump { name } => self
+                .backups
+                .iter()
+                .find(|b| b.directory_name.as_str() == name.as_str())
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no backup named {}", name))),
+        }
+    }
+}
+
+/// Which backup to operate on when reading from a datastore.
+pub enum ReadOptions {
+    Latest,
+    Dump { name: String },
+}
+
+/// Summary of a [`Datastore::garbage_collect`] run, mirroring Proxmox's
+/// `GarbageCollectionStatus`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GarbageCollectionStatus {
+    pub bytes_freed: u64,
+    pub objects_removed: u64,
+}
+
+/// Outcome of re-checking one [`PartManifest`]'s stored bytes against its
+/// recorded size and digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartVerificationStatus {
+    Ok,
+    /// Stored bytes exist but their size or digest no longer match.
+    Corrupt,
+    /// A chunk referenced by the part is no longer on disk.
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartVerification {
+    pub file_part: u16,
+    pub status: PartVerificationStatus,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupVerification {
+    pub directory_name: String,
+    pub parts: Vec<PartVerification>,
+}
+
+impl BackupVerification {
+    pub fn is_healthy(&self) -> bool {
+        self.parts
+            .iter()
+            .all(|part| part.status == PartVerificationStatus::Ok)
+    }
+}
+
+/// A place backups can be written to, read from and pruned.
+pub trait Datastore {
+    fn index_file(&self) -> Result<IndexFile, Error>;
+    fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error>;
+    fn write(&self, file_part: u16, data: types::Bytes) -> Result<(), Error>;
+    fn read(
+        &self,
+        options: &ReadOptions,
+        data_callback: &mut dyn FnMut(types::Bytes),
+    ) -> Result<(), Error>;
+    fn compression_enabled(&self) -> bool;
+    fn set_compression(&mut self, enable: bool);
+    fn encryption_key(&self) -> &Option<String>;
+    fn set_encryption_key(&mut self, key: String);
+    fn set_dump_name(&mut self, name: String);
+    fn delete_by_name(&self, name: String) -> Result<(), Error>;
+
+    /// Reads `file_part` of `directory_name` exactly as stored on disk
+    /// (post-compress/post-encrypt, not yet reversed). Used by
+    /// [`copy_backup`] to move a backup between datastores without paying
+    /// for a decrypt/decompress/re-encrypt round trip.
+    fn read_part_raw(&self, directory_name: &str, file_part: u16) -> Result<types::Bytes, Error>;
+
+    /// Writes `data` for `file_part` of `directory_name` verbatim, tagging
+    /// the resulting [`Backup`] with the given `compressed`/`encrypted`
+    /// flags instead of this datastore's own settings.
+    fn write_part_raw(
+        &self,
+        directory_name: &str,
+        file_part: u16,
+        data: types::Bytes,
+        compressed: bool,
+        encrypted: bool,
+    ) -> Result<(), Error>;
+
+    /// Mark-and-sweep: removes every object in the datastore that is no
+    /// longer referenced by a surviving [`Backup`] (e.g. chunks orphaned by
+    /// a deleted backup, or left behind by a write that crashed before the
+    /// index file was updated). Objects younger than `grace_period` are left
+    /// alone so an in-flight `write` is never raced.
+    fn garbage_collect(&self, args: &DumpGcArgs) -> Result<GarbageCollectionStatus, Error>;
+
+    /// Re-reads every part of the selected backup(s) and recomputes its
+    /// digest, without needing the encryption key since [`PartManifest`]
+    /// digests are taken over the final on-disk bytes.
+    fn verify(&self, args: &DumpVerifyArgs) -> Result<Vec<BackupVerification>, Error>;
+
+    /// Deletes backups matching a single exact name, an age cutoff, a "keep
+    /// the N most recent" rule, or a grandfather-father-son retention policy
+    /// (`keep_daily`/`keep_weekly`/`keep_monthly`/`keep_yearly`) — a backup
+    /// retained by any tier of the policy survives. `args.dry_run` logs what
+    /// would be kept/pruned instead of deleting anything.
+    fn delete(&self, args: &DumpDeleteArgs) -> Result<(), Error> {
+        if let Some(name) = &args.dump {
+            if args.dry_run {
+                info!("[dry-run] would prune dump: {}", name);
+                return Ok(());
+            }
+            return self.delete_by_name(name.to_string());
+        }
+
+        let index_file = self.index_file()?;
+        let mut backups = index_file.backups.clone();
+        backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        let has_gfs_tier = args.keep_daily.is_some()
+            || args.keep_weekly.is_some()
+            || args.keep_monthly.is_some()
+            || args.keep_yearly.is_some();
+
+        let to_delete: Vec<String> = if let Some(keep_last) = args.keep_last {
+            backups
+                .iter()
+                .skip(keep_last)
+                .map(|b| b.directory_name.clone())
+                .collect()
+        } else if let Some(older_than) = &args.older_than {
+            let cutoff = (Utc::now() - parse_duration(older_than)?).timestamp_millis() as u128;
+
+            backups
+                .iter()
+                .filter(|b| b.created_at < cutoff)
+                .map(|b| b.directory_name.clone())
+                .collect()
+        } else if has_gfs_tier {
+            let mut survivors: HashSet<String> = HashSet::new();
+
+            if let Some(keep) = args.keep_daily {
+                survivors.extend(keep_newest_per_bucket(&backups, keep, |b| {
+                    gfs_bucket_date(b.created_at).format("%Y-%m-%d").to_string()
+                }));
+            }
+            if let Some(keep) = args.keep_weekly {
+                survivors.extend(keep_newest_per_bucket(&backups, keep, |b| {
+                    let week = gfs_bucket_date(b.created_at).iso_week();
+                    format!("{}-W{:02}", week.year(), week.week())
+                }));
+            }
+            if let Some(keep) = args.keep_monthly {
+                survivors.extend(keep_newest_per_bucket(&backups, keep, |b| {
+                    gfs_bucket_date(b.created_at).format("%Y-%m").to_string()
+                }));
+            }
+            if let Some(keep) = args.keep_yearly {
+                survivors.extend(keep_newest_per_bucket(&backups, keep, |b| {
+                    gfs_bucket_date(b.created_at).format("%Y").to_string()
+                }));
+            }
+
+            backups
+                .iter()
+                .filter(|b| !survivors.contains(&b.directory_name))
+                .map(|b| b.directory_name.clone())
+                .collect()
+        } else {
+            vec![]
+        };
+
+        if args.dry_run {
+            for backup in &backups {
+                if to_delete.contains(&backup.directory_name) {
+                    info!("[dry-run] would prune dump: {}", backup.directory_name);
+                } else {
+                    info!("[dry-run] would keep dump: {}", backup.directory_name);
+                }
+            }
+            return Ok(());
+        }
+
+        for name in to_delete {
+            self.delete_by_name(name)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn gfs_bucket_date(created_at: u128) -> chrono::DateTime<Utc> {
+    Utc.timestamp_millis(created_at as i64)
+}
+
+/// Keeps the newest backup in each of the `keep_buckets` most recent buckets
+/// produced by `bucket_key`. `backups` must already be sorted newest-first.
+fn keep_newest_per_bucket<F>(backups: &[Backup], keep_buckets: usize, bucket_key: F) -> HashSet<String>
+where
+    F: Fn(&Backup) -> String,
+{
+    let mut newest_in_bucket: Vec<(String, &str)> = Vec::new();
+
+    for backup in backups {
+        let bucket = bucket_key(backup);
+        if !newest_in_bucket.iter().any(|(b, _)| b == &bucket) {
+            newest_in_bucket.push((bucket, backup.directory_name.as_str()));
+        }
+    }
+
+    newest_in_bucket
+        .into_iter()
+        .take(keep_buckets)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Copies one backup from `source` to `destination`. Each part's bytes are
+/// streamed out of `source` and into `destination` verbatim — no
+/// decrypt/decompress/re-encrypt — and the destination's `Backup` entry is
+/// reconstructed with the source's original `compressed`/`encrypted` flags
+/// and `created_at`.
+///
+/// Since the copy never decrypts/re-encrypts, `source` and `destination`
+/// must share the same `encryption_key` whenever the backup being copied is
+/// encrypted — otherwise the destination would end up with ciphertext it
+/// has no way to read back.
+pub fn copy_backup(
+    source: &dyn Datastore,
+    destination: &dyn Datastore,
+    options: &ReadOptions,
+) -> Result<(), Error> {
+    let source_index_file = source.index_file()?;
+    let backup = source_index_file.find_backup(options)?;
+
+    if backup.encrypted && source.encryption_key() != destination.encryption_key() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot copy an encrypted backup between datastores with different encryption_keys",
+        ));
+    }
+
+    for part in &backup.parts {
+        let data = source.read_part_raw(&backup.directory_name, part.file_part)?;
+        destination.write_part_raw(
+            &backup.directory_name,
+            part.file_part,
+            data,
+            backup.compressed,
+            backup.encrypted,
+        )?;
+    }
+
+    // write_part_raw stamped the new backup with the copy time; restore the
+    // original created_at so retention policies treat it the same as before.
+    let mut destination_index_file = destination.index_file()?;
+    if let Some(copied_backup) = destination_index_file
+        .backups
+        .iter_mut()
+        .find(|b| b.directory_name == backup.directory_name)
+    {
+        copied_backup.created_at = backup.created_at;
+    }
+    destination.write_index_file(&destination_index_file)
+}
+
+/// Parses a short duration string such as `"7d"` or `"1h"` into a
+/// [`chrono::Duration`].
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, Error> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid duration: {}", value));
+
+    if let Some(days) = value.strip_suffix('d') {
+        return days.parse().map(Duration::days).map_err(|_| invalid());
+    }
+
+    if let Some(hours) = value.strip_suffix('h') {
+        return hours.parse().map(Duration::hours).map_err(|_| invalid());
+    }
+
+    Err(invalid())
+}
+
+/// Gzip-compresses `data`.
+pub fn compress(data: types::Bytes) -> Result<types::Bytes, Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&data)?;
+    encoder.finish()
+}
+
+/// Reverses [`compress`].
+pub fn decompress(data: types::Bytes) -> Result<types::Bytes, Error> {
+    let mut decoder = GzDecoder::new(Cursor::new(data));
+    let mut out = Vec::new();
+    decoder.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Encrypts `data` with `key` (ChaCha20-Poly1305, key stretched with SHA-256).
+pub fn encrypt(data: types::Bytes, key: &str) -> Result<types::Bytes, Error> {
+    crate::crypto::encrypt(data, key)
+}
+
+/// Reverses [`encrypt`].
+pub fn decrypt(data: types::Bytes, key: &str) -> Result<types::Bytes, Error> {
+    crate::crypto::decrypt(data, key)
+}
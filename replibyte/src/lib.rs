@@ -0,0 +1,6 @@
+pub mod cli;
+pub mod connector;
+pub mod crypto;
+pub mod datastore;
+pub mod types;
+pub mod utils;
@@ -0,0 +1,2 @@
+/// Raw bytes moving through the dump/restore pipeline.
+pub type Bytes = Vec<u8>;